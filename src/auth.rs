@@ -0,0 +1,252 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{web, Error, HttpResponse};
+use chrono::{Duration, Utc};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::db::{self, PgPool};
+use crate::models::ApiError;
+
+/// Shared secret used to sign and verify JWTs. In a real deployment this
+/// would come from the environment; for now it is hard-coded, like the
+/// DB connection settings in `db::build_pool`.
+pub const JWT_SECRET: &[u8] = b"change-me-in-production";
+
+const TOKEN_TTL_HOURS: i64 = 24;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Claims {
+    pub user_id: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+impl Claims {
+    fn new(user_id: String) -> Self {
+        let now = Utc::now();
+        Claims {
+            user_id,
+            iat: now.timestamp(),
+            exp: (now + Duration::hours(TOKEN_TTL_HOURS)).timestamp(),
+        }
+    }
+}
+
+pub fn issue_token(user_id: String) -> Result<String, jsonwebtoken::errors::Error> {
+    encode(
+        &Header::default(),
+        &Claims::new(user_id),
+        &EncodingKey::from_secret(JWT_SECRET),
+    )
+}
+
+pub fn verify_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(JWT_SECRET),
+        &Validation::default(),
+    )
+    .map(|data| data.claims)
+}
+
+/// Pulls the token out of a `Authorization: Bearer <token>` header, shared
+/// by `RequireAuthMiddleware` and `require_bearer` below.
+fn bearer_token(headers: &actix_web::http::HeaderMap) -> Option<&str> {
+    headers
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+/// Extracts and verifies the bearer token carried by `req`, returning the
+/// `401` response a handler should send back directly when it's missing
+/// or invalid. For routes that share a path with a public GET route (so
+/// `RequireAuth`'s scope-level wrap can't apply to only the write
+/// method) instead of the `RequireAuth` middleware.
+pub fn require_bearer(req: &actix_web::HttpRequest) -> Result<Claims, HttpResponse> {
+    let token = bearer_token(req.headers()).ok_or_else(|| {
+        HttpResponse::Unauthorized().json(ApiError::new("missing bearer token".to_string()))
+    })?;
+
+    verify_token(token).map_err(|err| {
+        info!("rejected request with invalid token: {}", err);
+        HttpResponse::Unauthorized().json(ApiError::new("invalid or expired token".to_string()))
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    username: String,
+    password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoginResponse {
+    token: String,
+}
+
+/// `POST /login` - verifies `username`/`password` against the `users` table
+/// and, on success, issues an HS256 JWT carrying the user id.
+pub async fn login(pool: web::Data<PgPool>, body: web::Json<LoginRequest>) -> HttpResponse {
+    let conn = match db::checkout(&pool).await {
+        Ok(conn) => conn,
+        Err(resp) => return resp,
+    };
+
+    let row = conn
+        .query_opt(
+            "select id, password_hash from users where username = $1::TEXT",
+            &[&body.username],
+        )
+        .await;
+
+    let row = match row {
+        Ok(row) => row,
+        Err(err) => {
+            error!("failed to look up user {}: {}", body.username, err);
+            return HttpResponse::InternalServerError()
+                .json(ApiError::new("failed to verify credentials".to_string()));
+        }
+    };
+
+    let row = match row {
+        Some(row) => row,
+        None => {
+            return HttpResponse::Unauthorized().json(ApiError::new("invalid credentials".to_string()))
+        }
+    };
+
+    let user_id: i32 = row.get("id");
+    let password_hash: String = row.get("password_hash");
+
+    match bcrypt::verify(&body.password, &password_hash) {
+        Ok(true) => {}
+        _ => return HttpResponse::Unauthorized().json(ApiError::new("invalid credentials".to_string())),
+    }
+
+    match issue_token(user_id.to_string()) {
+        Ok(token) => {
+            info!("issued token for user {}", user_id);
+            HttpResponse::Ok().json(LoginResponse { token })
+        }
+        Err(err) => {
+            error!("failed to sign token for user {}: {}", user_id, err);
+            HttpResponse::InternalServerError().json(ApiError::new("failed to issue token".to_string()))
+        }
+    }
+}
+
+/// Middleware that requires a valid `Authorization: Bearer <token>` header,
+/// rejecting the request with `401` when the header is missing or the token
+/// is expired/has a bad signature.
+pub struct RequireAuth;
+
+impl<S, B> Transform<S> for RequireAuth
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequireAuthMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequireAuthMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+pub struct RequireAuthMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S, B> Service for RequireAuthMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+
+        let token = bearer_token(req.headers()).map(str::to_string);
+
+        Box::pin(async move {
+            let token = match token {
+                Some(token) => token,
+                None => {
+                    return Ok(req.into_response(
+                        HttpResponse::Unauthorized()
+                            .json(ApiError::new("missing bearer token".to_string())),
+                    ))
+                }
+            };
+
+            match verify_token(&token) {
+                Ok(claims) => {
+                    req.extensions_mut().insert(claims);
+                    service.borrow_mut().call(req).await
+                }
+                Err(err) => {
+                    info!("rejected request with invalid token: {}", err);
+                    Ok(req.into_response(
+                        HttpResponse::Unauthorized()
+                            .json(ApiError::new("invalid or expired token".to_string())),
+                    ))
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn issue_token_round_trips_through_verify_token() {
+        let token = issue_token("42".to_string()).expect("can sign a token");
+        let claims = verify_token(&token).expect("can verify a freshly issued token");
+
+        assert_eq!(claims.user_id, "42");
+        assert!(claims.exp > claims.iat);
+    }
+
+    #[test]
+    fn verify_token_rejects_an_expired_token() {
+        let claims = Claims {
+            user_id: "42".to_string(),
+            iat: (Utc::now() - Duration::hours(TOKEN_TTL_HOURS + 1)).timestamp(),
+            exp: (Utc::now() - Duration::hours(1)).timestamp(),
+        };
+        let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(JWT_SECRET))
+            .expect("can sign a token");
+
+        assert!(verify_token(&token).is_err());
+    }
+
+    #[test]
+    fn verify_token_rejects_garbage() {
+        assert!(verify_token("not-a-jwt").is_err());
+    }
+}
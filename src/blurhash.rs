@@ -0,0 +1,195 @@
+//! A from-scratch BlurHash encoder: averages an image against a grid of
+//! cosine basis functions (a forward DCT) to get a DC "average color"
+//! term plus a handful of AC detail terms, then packs them into the
+//! compact base83 string format described at
+//! <https://github.com/woltapp/blurhash>.
+
+const BASE83_ALPHABET: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+const DEFAULT_COMPONENTS_X: u32 = 4;
+const DEFAULT_COMPONENTS_Y: u32 = 3;
+
+fn encode_base83(mut value: u32, digits: usize) -> String {
+    let mut out = vec![0u8; digits];
+    for slot in out.iter_mut().rev() {
+        *slot = BASE83_ALPHABET[(value % 83) as usize];
+        value /= 83;
+    }
+    String::from_utf8(out).expect("base83 alphabet is ASCII")
+}
+
+fn srgb_to_linear(value: u8) -> f64 {
+    let v = value as f64 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> u8 {
+    let v = value.clamp(0.0, 1.0);
+    let encoded = if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+fn sign(value: f64) -> f64 {
+    if value < 0.0 {
+        -1.0
+    } else {
+        1.0
+    }
+}
+
+/// Nonlinearly quantizes one AC channel value into the 0..=18 range that
+/// base83-packs three-at-a-time into a component word.
+fn quantize_ac(value: f64, max_value: f64) -> i64 {
+    let normalized = if max_value == 0.0 { 0.0 } else { value / max_value };
+    let quantized = (sign(normalized) * normalized.abs().powf(0.5) * 9.0 + 9.5).floor();
+    quantized.clamp(0.0, 18.0) as i64
+}
+
+#[derive(Clone, Copy)]
+struct Component {
+    r: f64,
+    g: f64,
+    b: f64,
+}
+
+/// Averages the (linear-light) image against the (i, j) cosine basis
+/// function - this is exactly a forward DCT coefficient.
+fn basis_average(pixels: &[(f64, f64, f64)], width: u32, height: u32, i: u32, j: u32) -> Component {
+    let normalization = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+
+    let mut r = 0.0;
+    let mut g = 0.0;
+    let mut b = 0.0;
+
+    for y in 0..height {
+        for x in 0..width {
+            let basis = normalization
+                * (std::f64::consts::PI * i as f64 * x as f64 / width as f64).cos()
+                * (std::f64::consts::PI * j as f64 * y as f64 / height as f64).cos();
+            let (pr, pg, pb) = pixels[(y * width + x) as usize];
+            r += basis * pr;
+            g += basis * pg;
+            b += basis * pb;
+        }
+    }
+
+    let scale = 1.0 / (width * height) as f64;
+    Component {
+        r: r * scale,
+        g: g * scale,
+        b: b * scale,
+    }
+}
+
+/// Encodes an `width`x`height` RGB8 buffer (e.g. a downscaled cover)
+/// into a BlurHash string using the default 4x3 component grid.
+pub fn encode(rgb: &[u8], width: u32, height: u32) -> String {
+    encode_with_components(rgb, width, height, DEFAULT_COMPONENTS_X, DEFAULT_COMPONENTS_Y)
+}
+
+pub fn encode_with_components(
+    rgb: &[u8],
+    width: u32,
+    height: u32,
+    components_x: u32,
+    components_y: u32,
+) -> String {
+    assert!((1..=9).contains(&components_x));
+    assert!((1..=9).contains(&components_y));
+
+    let pixels: Vec<(f64, f64, f64)> = rgb
+        .chunks_exact(3)
+        .map(|p| (srgb_to_linear(p[0]), srgb_to_linear(p[1]), srgb_to_linear(p[2])))
+        .collect();
+
+    let mut components = Vec::with_capacity((components_x * components_y) as usize);
+    for j in 0..components_y {
+        for i in 0..components_x {
+            components.push(basis_average(&pixels, width, height, i, j));
+        }
+    }
+
+    let dc = components[0];
+    let ac = &components[1..];
+
+    let mut hash = String::new();
+
+    let size_flag = (components_x - 1) + (components_y - 1) * 9;
+    hash.push_str(&encode_base83(size_flag, 1));
+
+    let max_ac = ac
+        .iter()
+        .flat_map(|c| [c.r.abs(), c.g.abs(), c.b.abs()])
+        .fold(0.0_f64, f64::max);
+
+    let quantized_max_ac = if ac.is_empty() {
+        0
+    } else {
+        ((max_ac * 166.0 - 0.5).floor().clamp(0.0, 82.0)) as u32
+    };
+    hash.push_str(&encode_base83(quantized_max_ac, 1));
+
+    let dc_value = ((linear_to_srgb(dc.r) as u32) << 16)
+        | ((linear_to_srgb(dc.g) as u32) << 8)
+        | (linear_to_srgb(dc.b) as u32);
+    hash.push_str(&encode_base83(dc_value, 4));
+
+    let actual_max_ac = if ac.is_empty() {
+        1.0
+    } else {
+        (quantized_max_ac as f64 + 1.0) / 166.0
+    };
+
+    for component in ac {
+        let qr = quantize_ac(component.r, actual_max_ac);
+        let qg = quantize_ac(component.g, actual_max_ac);
+        let qb = quantize_ac(component.b, actual_max_ac);
+        let value = (qr * 19 * 19 + qg * 19 + qb) as u32;
+        hash.push_str(&encode_base83(value, 2));
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_base83_pads_and_wraps_to_the_alphabet() {
+        assert_eq!(encode_base83(0, 1), "0");
+        assert_eq!(encode_base83(82, 1), "~");
+        assert_eq!(encode_base83(1, 4), "0001");
+    }
+
+    #[test]
+    fn quantize_ac_maps_zero_to_the_midpoint() {
+        assert_eq!(quantize_ac(0.0, 1.0), 9);
+    }
+
+    #[test]
+    fn quantize_ac_is_antisymmetric_and_clamped() {
+        assert_eq!(quantize_ac(1.0, 1.0), 18);
+        assert_eq!(quantize_ac(-1.0, 1.0), 0);
+        assert_eq!(quantize_ac(1.0, 0.0), 9);
+    }
+
+    #[test]
+    fn encode_produces_a_stable_length_hash_for_the_default_grid() {
+        let flat_gray = vec![128u8; 32 * 32 * 3];
+        let hash = encode(&flat_gray, 32, 32);
+
+        // 1 (size flag) + 1 (max AC) + 4 (DC) + 2 per remaining AC component.
+        let expected_len = 1 + 1 + 4 + 2 * (DEFAULT_COMPONENTS_X * DEFAULT_COMPONENTS_Y - 1) as usize;
+        assert_eq!(hash.len(), expected_len);
+    }
+}
@@ -0,0 +1,103 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use actix_web::client;
+use log::error;
+
+use crate::blurhash;
+use crate::db::PgClient;
+use crate::models::Book;
+
+/// Where cover images actually live. An abstraction over this (rather
+/// than hard-coding filesystem paths into the handlers) lets the
+/// on-disk layout used today be swapped for e.g. an object store later.
+pub trait CoverStore: Send + Sync {
+    /// Persists `bytes` for `book_id` and returns the key to pass to
+    /// `load` later (this is what gets stored as `Book::cover_path`).
+    fn save(&self, book_id: i32, bytes: &[u8]) -> io::Result<String>;
+    fn load(&self, key: &str) -> io::Result<Vec<u8>>;
+}
+
+/// Stores covers as flat files under a base directory, named by book id.
+pub struct FilesystemCoverStore {
+    base_dir: PathBuf,
+}
+
+impl FilesystemCoverStore {
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        let base_dir = base_dir.into();
+        fs::create_dir_all(&base_dir).ok();
+        FilesystemCoverStore { base_dir }
+    }
+}
+
+impl CoverStore for FilesystemCoverStore {
+    fn save(&self, book_id: i32, bytes: &[u8]) -> io::Result<String> {
+        let key = format!("{}.jpg", book_id);
+        fs::write(self.base_dir.join(&key), bytes)?;
+        Ok(key)
+    }
+
+    fn load(&self, key: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.base_dir.join(key))
+    }
+}
+
+/// Side length of the grid the cover is downscaled to before the
+/// BlurHash components are computed from it.
+const BLURHASH_GRID: u32 = 32;
+
+/// Decodes a cover image and computes its BlurHash placeholder,
+/// returning `None` if the bytes aren't a decodable image.
+pub fn compute_blurhash(bytes: &[u8]) -> Option<String> {
+    let image = image::load_from_memory(bytes).ok()?;
+    let small = image.resize_exact(
+        BLURHASH_GRID,
+        BLURHASH_GRID,
+        image::imageops::FilterType::Triangle,
+    );
+    let rgb = small.to_rgb8();
+
+    Some(blurhash::encode(rgb.as_raw(), BLURHASH_GRID, BLURHASH_GRID))
+}
+
+/// Downloads a cover image, persists it through the configured
+/// `CoverStore`, computes its BlurHash placeholder, and records both on
+/// the book row. Failures are logged and otherwise non-fatal to the
+/// import - a missing cover shouldn't drop the book.
+pub(crate) async fn ingest_cover(conn: &PgClient, store: &Arc<dyn CoverStore>, book_id: i32, url: &str) {
+    let client = client::Client::new();
+
+    let resp = match client.get(url).send().await {
+        Ok(resp) => resp,
+        Err(err) => {
+            error!("failed to download cover for book {}: {}", book_id, err);
+            return;
+        }
+    };
+
+    let mut resp = resp;
+    let bytes = match resp.body().await {
+        Ok(bytes) => bytes.to_vec(),
+        Err(err) => {
+            error!("failed to read cover body for book {}: {}", book_id, err);
+            return;
+        }
+    };
+
+    let cover_path = match store.save(book_id, &bytes) {
+        Ok(path) => path,
+        Err(err) => {
+            error!("failed to store cover for book {}: {}", book_id, err);
+            return;
+        }
+    };
+
+    let blurhash = compute_blurhash(&bytes).unwrap_or_default();
+
+    if let Err(err) = Book::update_cover(conn, book_id, &cover_path, &blurhash).await {
+        error!("failed to record cover for book {}: {}", book_id, err);
+    }
+}
@@ -0,0 +1,106 @@
+use deadpool_postgres::{Client, Config, ManagerConfig, Pool, RecyclingMethod, Runtime};
+use serde::Serialize;
+use tokio_postgres::NoTls;
+
+use actix_web::{web, HttpResponse};
+use log::{error, info};
+
+use crate::models::ApiError;
+
+pub type PgPool = Pool;
+pub type PgClient = Client;
+
+mod embedded {
+    use refinery::embed_migrations;
+    embed_migrations!("migrations");
+}
+
+type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+pub fn build_pool() -> PgPool {
+    let mut config = Config::new();
+    config.host = Some("localhost".to_string());
+    config.user = Some("postgres".to_string());
+    config.password = Some("example".to_string());
+    config.manager = Some(ManagerConfig {
+        recycling_method: RecyclingMethod::Fast,
+    });
+
+    config
+        .create_pool(Some(Runtime::Tokio1), NoTls)
+        .expect("can build DB pool")
+}
+
+/// Checks out a connection, mapping pool exhaustion/connectivity errors
+/// to the `503` response handlers should return instead of panicking.
+pub async fn checkout(pool: &PgPool) -> Result<PgClient, HttpResponse> {
+    pool.get().await.map_err(|err| {
+        error!("failed to check out DB connection: {}", err);
+        HttpResponse::ServiceUnavailable()
+            .json(ApiError::new("database unavailable".to_string()))
+    })
+}
+
+/// Runs every pending migration against `pool` and returns the highest
+/// applied version.
+pub async fn run_migrations(pool: &PgPool) -> Result<Option<i32>, BoxError> {
+    let mut client = pool.get().await?;
+
+    info!("running DB migrations...");
+    let report = embedded::migrations::runner().run_async(&mut *client).await?;
+
+    for migration in report.applied_migrations() {
+        info!(
+            "migration applied - name: {}, version: {}",
+            migration.name(),
+            migration.version()
+        );
+    }
+    info!("DB migrations finished");
+
+    current_migration_version(&client).await
+}
+
+/// Reads the highest version recorded in refinery's own history table,
+/// independent of whether this process ran any migrations itself.
+pub async fn current_migration_version(client: &PgClient) -> Result<Option<i32>, BoxError> {
+    let row = client
+        .query_opt(
+            "select version from refinery_schema_history order by version desc limit 1",
+            &[],
+        )
+        .await?;
+
+    Ok(row.map(|row| row.get("version")))
+}
+
+#[derive(Debug, Serialize)]
+struct HealthStatus {
+    database: &'static str,
+    migration_version: Option<i32>,
+}
+
+/// `GET /health` - reports DB connectivity and the currently-applied
+/// migration version.
+pub async fn health(pool: web::Data<PgPool>) -> HttpResponse {
+    let client = match pool.get().await {
+        Ok(client) => client,
+        Err(err) => {
+            error!("health check failed to reach the database: {}", err);
+            return HttpResponse::ServiceUnavailable()
+                .json(ApiError::new("database unreachable".to_string()));
+        }
+    };
+
+    match current_migration_version(&client).await {
+        Ok(migration_version) => HttpResponse::Ok().json(HealthStatus {
+            database: "ok",
+            migration_version,
+        }),
+        Err(err) => {
+            error!("health check failed to read migration history: {}", err);
+            HttpResponse::ServiceUnavailable()
+                .json(ApiError::new("database unreachable".to_string()))
+        }
+    }
+}
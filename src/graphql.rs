@@ -0,0 +1,182 @@
+use async_graphql::{Context, EmptySubscription, Object, Schema, SimpleObject};
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::auth::Claims;
+use crate::db::PgPool;
+use crate::jobs::JobQueue;
+use crate::models::Book as DbBook;
+use crate::search::SearchIndex;
+use actix_web::web;
+
+/// Mirrors `RequireAuth`'s check for the mutation resolvers: `/graphql`
+/// sits outside that middleware's scope (queries need to stay public),
+/// so each mutation re-checks the bearer token threaded in as query data
+/// by the `graphql` handler instead.
+fn require_auth(ctx: &Context<'_>) -> async_graphql::Result<()> {
+    match ctx.data::<Option<Claims>>() {
+        Ok(Some(_)) => Ok(()),
+        _ => Err("missing or invalid bearer token".into()),
+    }
+}
+
+pub type BooksSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+/// GraphQL projection of `Book` - a separate type from the REST one so
+/// each surface can evolve its own field set without fighting the
+/// other's serde attributes.
+#[derive(SimpleObject)]
+pub struct GqlBook {
+    id: i32,
+    title: String,
+    authors: Vec<String>,
+    publication_date: NaiveDate,
+    page_count: Option<i32>,
+    categories: Option<Vec<String>>,
+    average_rating: Option<f64>,
+}
+
+impl From<DbBook> for GqlBook {
+    fn from(book: DbBook) -> Self {
+        GqlBook {
+            id: book.id,
+            title: book.title,
+            authors: book.authors,
+            publication_date: book.publication_date,
+            page_count: book.page_count,
+            categories: book.categories,
+            average_rating: book.average_rating,
+        }
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Paginated/filterable book listing; passing `search` ranks through
+    /// the same inverted index `GET /books/search` uses instead of
+    /// falling back to `OFFSET`/`LIMIT`.
+    async fn books(
+        &self,
+        ctx: &Context<'_>,
+        offset: Option<i32>,
+        limit: Option<i32>,
+        search: Option<String>,
+    ) -> async_graphql::Result<Vec<GqlBook>> {
+        let pool = ctx.data::<PgPool>()?;
+        let conn = pool.get().await?;
+
+        if let Some(search) = search {
+            let index = ctx.data::<web::Data<SearchIndex>>()?;
+            let limit = limit.unwrap_or(20).max(0) as usize;
+            let ranked_ids = index.search(&search, limit);
+
+            if ranked_ids.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            let rows = conn.query(
+                "select id, name, author, publication_date, cover_path, blurhash, \
+                 page_count, categories, average_rating from books where id = any($1::INT[])",
+                &[&ranked_ids],
+            ).await?;
+
+            let mut books_by_id: std::collections::HashMap<i32, DbBook> = rows
+                .iter()
+                .map(|rec| {
+                    let book = DbBook::from_row(rec);
+                    (book.id, book)
+                })
+                .collect();
+
+            return Ok(ranked_ids
+                .into_iter()
+                .filter_map(|id| books_by_id.remove(&id))
+                .map(GqlBook::from)
+                .collect());
+        }
+
+        let rows = conn.query(
+            "select id, name, author, publication_date, cover_path, blurhash, \
+             page_count, categories, average_rating from books offset $1::INT limit $2::INT",
+            &[&offset.unwrap_or(0), &limit.unwrap_or(10)],
+        ).await?;
+
+        Ok(rows.iter().map(DbBook::from_row).map(GqlBook::from).collect())
+    }
+
+    async fn book(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<Option<GqlBook>> {
+        let pool = ctx.data::<PgPool>()?;
+        let conn = pool.get().await?;
+
+        let row = conn.query_opt(
+            "select id, name, author, publication_date, cover_path, blurhash, \
+             page_count, categories, average_rating from books where id = $1::INT",
+            &[&id],
+        ).await?;
+
+        Ok(row.map(|rec| GqlBook::from(DbBook::from_row(&rec))))
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    async fn add_book(
+        &self,
+        ctx: &Context<'_>,
+        title: String,
+        authors: Vec<String>,
+        publication_date: NaiveDate,
+    ) -> async_graphql::Result<GqlBook> {
+        require_auth(ctx)?;
+
+        let pool = ctx.data::<PgPool>()?;
+        let conn = pool.get().await?;
+        let index = ctx.data::<web::Data<SearchIndex>>()?;
+
+        let mut book = DbBook::new(0, title, authors, publication_date);
+        book.id = book.save(&conn).await?;
+
+        index.rebuild(&DbBook::all(&conn).await?);
+
+        Ok(GqlBook::from(book))
+    }
+
+    async fn delete_book(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<bool> {
+        require_auth(ctx)?;
+
+        let pool = ctx.data::<PgPool>()?;
+        let conn = pool.get().await?;
+
+        let affected = conn
+            .execute("delete from books where id = $1::INTEGER", &[&id])
+            .await?;
+
+        Ok(affected > 0)
+    }
+
+    /// Enqueues a background import job, mirroring `POST /books/import`,
+    /// and returns its job id for polling via `GET /jobs/{id}`.
+    async fn import_books(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        count: Option<u32>,
+    ) -> async_graphql::Result<Uuid> {
+        require_auth(ctx)?;
+
+        let queue = ctx.data::<web::Data<JobQueue>>()?;
+        Ok(queue.enqueue(query, count))
+    }
+}
+
+pub fn build_schema(pool: PgPool, index: web::Data<SearchIndex>, queue: web::Data<JobQueue>) -> BooksSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .data(pool)
+        .data(index)
+        .data(queue)
+        .finish()
+}
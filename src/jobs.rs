@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use log::error;
+use serde::Serialize;
+use tokio::sync::{mpsc, Mutex};
+use uuid::Uuid;
+
+use crate::covers::{self, CoverStore};
+use crate::db::PgPool;
+use crate::models::{Book, GoogleBooksRoot};
+use crate::search::SearchIndex;
+use actix_web::{client, web};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub state: JobState,
+    pub books_imported: u32,
+    pub last_error: Option<String>,
+}
+
+impl JobStatus {
+    fn queued() -> Self {
+        JobStatus {
+            state: JobState::Queued,
+            books_imported: 0,
+            last_error: None,
+        }
+    }
+}
+
+/// Google Books caps `maxResults` at 40 per request.
+const MAX_IMPORT_COUNT: u32 = 40;
+
+struct ImportJob {
+    id: Uuid,
+    query: String,
+    count: Option<u32>,
+}
+
+/// Shared job table plus the sending half of the work queue. Handlers
+/// only ever call `enqueue`/`status`; the worker pool spawned by
+/// `spawn_workers` is the only thing that mutates a job past `Queued`.
+pub struct JobQueue {
+    statuses: RwLock<HashMap<Uuid, JobStatus>>,
+    sender: mpsc::UnboundedSender<ImportJob>,
+}
+
+impl JobQueue {
+    pub fn enqueue(&self, query: String, count: Option<u32>) -> Uuid {
+        let id = Uuid::new_v4();
+        self.statuses.write().unwrap().insert(id, JobStatus::queued());
+
+        // The receiving end lives for the lifetime of the server, so this
+        // only fails if every worker task has panicked.
+        let _ = self.sender.send(ImportJob { id, query, count });
+
+        id
+    }
+
+    pub fn status(&self, id: &Uuid) -> Option<JobStatus> {
+        self.statuses.read().unwrap().get(id).cloned()
+    }
+
+    fn set_state(&self, id: Uuid, state: JobState) {
+        if let Some(status) = self.statuses.write().unwrap().get_mut(&id) {
+            status.state = state;
+        }
+    }
+
+    fn record_progress(&self, id: Uuid, books_imported: u32) {
+        if let Some(status) = self.statuses.write().unwrap().get_mut(&id) {
+            status.books_imported = books_imported;
+        }
+    }
+
+    fn record_error(&self, id: Uuid, error: String) {
+        if let Some(status) = self.statuses.write().unwrap().get_mut(&id) {
+            status.last_error = Some(error);
+        }
+    }
+}
+
+/// Spawns `worker_count` tasks draining the import queue on the
+/// actix/tokio runtime and returns the shared queue handlers enqueue
+/// jobs onto.
+pub fn spawn_workers(
+    pool: PgPool,
+    index: web::Data<SearchIndex>,
+    store: Arc<dyn CoverStore>,
+    worker_count: usize,
+) -> web::Data<JobQueue> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    let queue = web::Data::new(JobQueue {
+        statuses: RwLock::new(HashMap::new()),
+        sender,
+    });
+
+    for _ in 0..worker_count {
+        let receiver = Arc::clone(&receiver);
+        let pool = pool.clone();
+        let index = index.clone();
+        let store = Arc::clone(&store);
+        let queue = queue.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let job = receiver.lock().await.recv().await;
+                let job = match job {
+                    Some(job) => job,
+                    None => break,
+                };
+
+                run_import_job(job, &pool, &index, &store, &queue).await;
+            }
+        });
+    }
+
+    queue
+}
+
+/// Fetches the Google Books results for a job's query and inserts each
+/// volume, reporting progress and any per-book date-parse problems onto
+/// the job status instead of silently skipping them.
+async fn run_import_job(
+    job: ImportJob,
+    pool: &PgPool,
+    index: &SearchIndex,
+    store: &Arc<dyn CoverStore>,
+    queue: &JobQueue,
+) {
+    queue.set_state(job.id, JobState::Running);
+
+    let conn = match pool.get().await {
+        Ok(conn) => conn,
+        Err(err) => {
+            error!("import job {} failed to check out a DB connection: {}", job.id, err);
+            queue.record_error(job.id, "database unavailable".to_string());
+            queue.set_state(job.id, JobState::Failed);
+            return;
+        }
+    };
+
+    let url = match job.count {
+        Some(count) => format!(
+            "https://www.googleapis.com/books/v1/volumes?q={}&maxResults={}",
+            job.query,
+            count.min(MAX_IMPORT_COUNT)
+        ),
+        None => format!("https://www.googleapis.com/books/v1/volumes?q={}", job.query),
+    };
+
+    let client = client::Client::new();
+
+    let resp = match client.get(url).send().await {
+        Ok(resp) => resp,
+        Err(err) => {
+            error!("import job {} failed to call Google Books: {}", job.id, err);
+            queue.record_error(job.id, format!("request failed: {}", err));
+            queue.set_state(job.id, JobState::Failed);
+            return;
+        }
+    };
+
+    let mut resp = resp;
+    let body = match resp.body().await {
+        Ok(body) => body,
+        Err(err) => {
+            error!("import job {} failed to read response body: {}", job.id, err);
+            queue.record_error(job.id, format!("failed to read response: {}", err));
+            queue.set_state(job.id, JobState::Failed);
+            return;
+        }
+    };
+
+    let books: GoogleBooksRoot = match serde_json::from_slice(&body) {
+        Ok(books) => books,
+        Err(err) => {
+            error!("import job {} failed to parse response: {}", job.id, err);
+            queue.record_error(job.id, format!("failed to parse response: {}", err));
+            queue.set_state(job.id, JobState::Failed);
+            return;
+        }
+    };
+
+    let mut books_imported = 0;
+    let mut save_failures = 0;
+
+    for book in books.items {
+        let published_data = book.volume_info.published_date;
+
+        let publication_date = if published_data.len() == 4 {
+            published_data
+                .parse()
+                .ok()
+                .map(|year| chrono::NaiveDate::from_ymd(year, 1, 1))
+        } else if published_data.len() == 10 {
+            chrono::NaiveDate::parse_from_str(&published_data, "%Y-%m-%d").ok()
+        } else {
+            Some(chrono::NaiveDate::from_ymd(0, 1, 1))
+        };
+
+        let publication_date = match publication_date {
+            Some(date) => date,
+            None => {
+                let message = format!("could not parse date '{}', defaulting it", published_data);
+                error!("import job {}: {}", job.id, message);
+                queue.record_error(job.id, message);
+                chrono::NaiveDate::from_ymd(0, 1, 1)
+            }
+        };
+
+        let thumbnail = book
+            .volume_info
+            .image_links
+            .as_ref()
+            .map(|links| links.thumbnail.clone());
+
+        let new_book = Book::new(
+            0,
+            book.volume_info.title,
+            book.volume_info.authors,
+            publication_date,
+        )
+        .with_metadata(
+            book.volume_info.page_count.map(|count| count as i32),
+            book.volume_info.categories,
+            book.volume_info.average_rating,
+        );
+
+        let new_id = match new_book.save(&conn).await {
+            Ok(id) => id,
+            Err(err) => {
+                let message = format!("failed to save book '{}': {}", new_book.title, err);
+                error!("import job {}: {}", job.id, message);
+                queue.record_error(job.id, message);
+                save_failures += 1;
+                continue;
+            }
+        };
+
+        if let Some(thumbnail) = thumbnail {
+            covers::ingest_cover(&conn, store, new_id, &thumbnail).await;
+        }
+
+        books_imported += 1;
+        queue.record_progress(job.id, books_imported);
+    }
+
+    match Book::all(&conn).await {
+        Ok(books) => index.rebuild(&books),
+        Err(err) => error!("import job {} failed to rebuild the search index: {}", job.id, err),
+    }
+
+    // A result set where every save failed is a failed import, not a
+    // successful one that happened to import zero books.
+    if books_imported == 0 && save_failures > 0 {
+        queue.set_state(job.id, JobState::Failed);
+    } else {
+        queue.set_state(job.id, JobState::Done);
+    }
+}
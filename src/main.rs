@@ -1,30 +1,7 @@
 use actix_web::{error, middleware, web, App, Error, HttpRequest, HttpResponse, HttpServer};
 use futures::StreamExt;
 use json::JsonValue;
-use r2d2_postgres::r2d2::Pool;
-use r2d2_postgres::{r2d2, PostgresConnectionManager};
 use serde::{Deserialize, Serialize};
-use tokio_postgres::NoTls;
-
-#[derive(Debug, Serialize, Deserialize)]
-struct Book {
-    #[serde(skip_deserializing)]
-    id: i32,
-    title: String,
-    author: String,
-    publication_year: i32,
-}
-
-impl Book {
-    pub fn new(id: i32, title: String, author: String, publication_year: i32) -> Self {
-        Book {
-            id,
-            title,
-            author,
-            publication_year,
-        }
-    }
-}
 
 #[derive(Debug, Serialize, Deserialize)]
 struct MyObj {
@@ -32,71 +9,22 @@ struct MyObj {
     number: i32,
 }
 
-mod embedded {
-    use refinery::embed_migrations;
-    embed_migrations!("migrations");
-}
+mod auth;
+mod blurhash;
+mod covers;
+mod db;
+mod graphql;
+mod handlers;
+mod jobs;
+mod models;
+mod search;
+mod sql;
 
 /// This handler uses json extractor
 async fn index() -> HttpResponse {
     HttpResponse::Ok().body("OK")
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Params {
-    #[serde(default)]
-    offset: i32,
-}
-
-async fn books_get(
-    pool: web::Data<Pool<PostgresConnectionManager<NoTls>>>,
-    req: HttpRequest,
-) -> HttpResponse {
-   let params = web::Query::<Params>::from_query(req.query_string()).unwrap();
-    println!("offset {}", params.offset);
-
-    let rows = pool.get().unwrap().query(
-        "select id, name, author, publication_year from books offset $1::INT limit $2::INT",
-        &[&params.offset, &10],
-    );
-
-    let books = rows
-        .unwrap()
-        .iter()
-        .map(|rec| {
-            Book::new(
-                rec.get("id"),
-                rec.get("name"),
-                rec.get("author"),
-                rec.get("publication_year"),
-            )
-        })
-        .collect::<Vec<Book>>();
-
-    HttpResponse::Ok().json(books)
-}
-
-async fn books_post(
-    pool: web::Data<Pool<PostgresConnectionManager<NoTls>>>,
-    item: web::Json<Book>,
-    req: HttpRequest,
-) -> HttpResponse {
-    println!("request: {:?}", req);
-    println!("model: {:?}", item);
-
-    let rows = pool
-        .get()
-        .unwrap()
-        .execute(
-            "insert into books (name, author, publication_year) values ($1::TEXT, $2::TEXT, $3::INT)",
-            &[&item.title, &item.author, &item.publication_year],
-        );
-
-    println!("{} rows updated", rows.unwrap());
-
-    HttpResponse::Created().json(item.0)
-}
-
 /// This handler uses json extractor with limit
 async fn extract_item(item: web::Json<MyObj>, req: HttpRequest) -> HttpResponse {
     println!("request: {:?}", req);
@@ -138,48 +66,43 @@ async fn index_mjsonrust(body: web::Bytes) -> Result<HttpResponse, Error> {
         .body(injson.dump()))
 }
 
-type Error1 = Box<dyn std::error::Error + Send + Sync + 'static>;
-
-#[tokio::main]
-async fn run_migrations() -> std::result::Result<(), Error1> {
-    println!("Running DB migrations...");
-    let (mut client, con) =
-        tokio_postgres::connect("host=localhost user=postgres password=example", NoTls).await?;
-
-    tokio::spawn(async move {
-        if let Err(e) = con.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
-    let migration_report = embedded::migrations::runner()
-        .run_async(&mut client)
-        .await?;
-    for migration in migration_report.applied_migrations() {
-        println!(
-            "Migration Applied -  Name: {}, Version: {}",
-            migration.name(),
-            migration.version()
-        );
-    }
-    println!("DB migrations finished!");
-
-    Ok(())
-}
-
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     std::env::set_var("RUST_LOG", "actix_web=debug");
     env_logger::init();
 
-    run_migrations().expect("can run DB migrations: {}");
+    let pool = db::build_pool();
+
+    db::run_migrations(&pool)
+        .await
+        .expect("can run DB migrations: {}");
 
-    let manager = PostgresConnectionManager::new(
-        "host=localhost user=postgres password=example"
-            .parse()
-            .unwrap(),
-        NoTls,
+    let search_index = web::Data::new(search::SearchIndex::new());
+    {
+        let conn = pool.get().await.expect("can check out a DB connection");
+        let books = models::Book::all(&conn)
+            .await
+            .expect("can load books to build the search index");
+        search_index.rebuild(&books);
+    }
+
+    let cover_store: web::Data<std::sync::Arc<dyn covers::CoverStore>> = web::Data::new(
+        std::sync::Arc::new(covers::FilesystemCoverStore::new("./covers")),
     );
-    let pool = r2d2::Pool::new(manager).unwrap();
+
+    const IMPORT_WORKER_COUNT: usize = 4;
+    let job_queue = jobs::spawn_workers(
+        pool.clone(),
+        search_index.clone(),
+        cover_store.get_ref().clone(),
+        IMPORT_WORKER_COUNT,
+    );
+
+    let graphql_schema = web::Data::new(graphql::build_schema(
+        pool.clone(),
+        search_index.clone(),
+        job_queue.clone(),
+    ));
 
     HttpServer::new(move || {
         App::new()
@@ -187,6 +110,10 @@ async fn main() -> std::io::Result<()> {
             .wrap(middleware::Logger::default())
             .data(web::JsonConfig::default().limit(4096))
             .data(pool.clone())
+            .app_data(search_index.clone())
+            .app_data(cover_store.clone())
+            .app_data(job_queue.clone())
+            .app_data(graphql_schema.clone())
             .service(web::resource("/extractor").route(web::post().to(index)))
             .service(
                 web::resource("/extractor2")
@@ -196,10 +123,30 @@ async fn main() -> std::io::Result<()> {
             .service(web::resource("/manual").route(web::post().to(index_manual)))
             .service(web::resource("/mjsonrust").route(web::post().to(index_mjsonrust)))
             .service(web::resource("/").route(web::get().to(index)))
+            .service(web::resource("/login").route(web::post().to(auth::login)))
+            .service(web::resource("/health").route(web::get().to(db::health)))
             .service(
+                // GET and POST share one resource - actix resolves by path
+                // first, so registering them as two separate resources on
+                // "/books" would make the second one unreachable. books_post
+                // checks its own bearer token for this reason; see its doc
+                // comment.
                 web::resource("/books")
-                    .route(web::get().to(books_get))
-                    .route(web::post().to(books_post)),
+                    .route(web::get().to(handlers::books_get))
+                    .route(web::post().to(handlers::books_post)),
+            )
+            .service(web::resource("/books/search").route(web::get().to(handlers::books_search)))
+            .service(web::resource("/books/{id}/cover").route(web::get().to(handlers::books_cover)))
+            .service(web::resource("/jobs/{id}").route(web::get().to(handlers::job_status)))
+            .service(web::resource("/graphql").route(web::post().to(handlers::graphql)))
+            .service(
+                web::scope("")
+                    .wrap(auth::RequireAuth)
+                    .service(handlers::books_delete)
+                    .service(
+                        web::resource("/books/import")
+                            .route(web::post().to(handlers::books_import)),
+                    ),
             )
     })
     .bind("127.0.0.1:8080")?
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::handlers::PgPool;
+use crate::db::PgClient;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ApiError {
@@ -20,6 +20,20 @@ pub struct Book {
     pub(crate) title: String,
     pub(crate) authors: Vec<String>,
     pub(crate) publication_date: chrono::NaiveDate,
+    /// Key into the configured `CoverStore`, not exposed to clients -
+    /// they fetch the image itself through `GET /books/{id}/cover`.
+    #[serde(skip)]
+    pub(crate) cover_path: Option<String>,
+    #[serde(skip_deserializing)]
+    pub(crate) blurhash: Option<String>,
+    /// Google Books metadata that the REST API ignored until the
+    /// GraphQL surface needed it.
+    #[serde(default, skip_deserializing)]
+    pub(crate) page_count: Option<i32>,
+    #[serde(default, skip_deserializing)]
+    pub(crate) categories: Option<Vec<String>>,
+    #[serde(default, skip_deserializing)]
+    pub(crate) average_rating: Option<f64>,
 }
 
 impl Book {
@@ -34,16 +48,89 @@ impl Book {
             title,
             authors,
             publication_date,
+            cover_path: None,
+            blurhash: None,
+            page_count: None,
+            categories: None,
+            average_rating: None,
         }
     }
 
-    pub fn save(&self, conn: &PgPool) -> i32 {
-        let rows = conn.get().unwrap().query_one(
-            "insert into books (name, author, publication_date) values ($1::TEXT, $2, $3) returning id",
-            &[&self.title, &self.authors, &self.publication_date],
-        );
+    /// Attaches the Google Books metadata fields that aren't part of the
+    /// base constructor, used by the import job.
+    pub fn with_metadata(
+        mut self,
+        page_count: Option<i32>,
+        categories: Option<Vec<String>>,
+        average_rating: Option<f64>,
+    ) -> Self {
+        self.page_count = page_count;
+        self.categories = categories;
+        self.average_rating = average_rating;
+        self
+    }
 
-        rows.unwrap().get(0)
+    pub async fn save(&self, conn: &PgClient) -> Result<i32, tokio_postgres::Error> {
+        let row = conn.query_one(
+            "insert into books (name, author, publication_date, page_count, categories, average_rating) \
+             values ($1::TEXT, $2, $3, $4::INT, $5, $6::DOUBLE PRECISION) returning id",
+            &[
+                &self.title,
+                &self.authors,
+                &self.publication_date,
+                &self.page_count,
+                &self.categories,
+                &self.average_rating,
+            ],
+        ).await?;
+
+        Ok(row.get(0))
+    }
+
+    /// Records where a downloaded cover was persisted and its BlurHash
+    /// placeholder, once an import job has fetched and stored it.
+    pub async fn update_cover(
+        conn: &PgClient,
+        id: i32,
+        cover_path: &str,
+        blurhash: &str,
+    ) -> Result<(), tokio_postgres::Error> {
+        conn.execute(
+            "update books set cover_path = $1::TEXT, blurhash = $2::TEXT where id = $3::INT",
+            &[&cover_path, &blurhash, &id],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fetches every book in the catalog, used to rebuild the in-memory
+    /// search index after startup and after each insert.
+    pub async fn all(conn: &PgClient) -> Result<Vec<Book>, tokio_postgres::Error> {
+        let rows = conn
+            .query(
+                "select id, name, author, publication_date, cover_path, blurhash, \
+                 page_count, categories, average_rating from books",
+                &[],
+            )
+            .await?;
+
+        Ok(rows.iter().map(Book::from_row).collect())
+    }
+
+    pub(crate) fn from_row(rec: &tokio_postgres::Row) -> Book {
+        let mut book = Book::new(
+            rec.get("id"),
+            rec.get("name"),
+            rec.get("author"),
+            rec.get("publication_date"),
+        );
+        book.cover_path = rec.get("cover_path");
+        book.blurhash = rec.get("blurhash");
+        book.page_count = rec.get("page_count");
+        book.categories = rec.get("categories");
+        book.average_rating = rec.get("average_rating");
+        book
     }
 }
 
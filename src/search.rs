@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+use crate::models::Book;
+
+/// Trivial stop words stripped from both indexed text and queries so they
+/// don't dominate the token space.
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "the", "of", "in", "on", "for", "to", "is", "it",
+];
+
+const TITLE_WEIGHT: i32 = 2;
+const AUTHOR_WEIGHT: i32 = 1;
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(|token| token.to_lowercase())
+        .filter(|token| !token.is_empty() && !STOP_WORDS.contains(&token.as_str()))
+        .collect()
+}
+
+/// In-memory inverted index over book titles and authors, rebuilt from the
+/// full `books` table on startup and after every insert (deleting a book
+/// does not rebuild it; stale ids are dropped when the matched rows are
+/// looked up in the table). Held behind an
+/// `RwLock` so concurrent searches never block each other, only the
+/// occasional rebuild takes the write lock.
+#[derive(Default)]
+pub struct SearchIndex {
+    title_index: RwLock<HashMap<String, Vec<i32>>>,
+    author_index: RwLock<HashMap<String, Vec<i32>>>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn rebuild(&self, books: &[Book]) {
+        let mut title_index: HashMap<String, Vec<i32>> = HashMap::new();
+        let mut author_index: HashMap<String, Vec<i32>> = HashMap::new();
+
+        for book in books {
+            for token in tokenize(&book.title) {
+                title_index.entry(token).or_default().push(book.id);
+            }
+            for author in &book.authors {
+                for token in tokenize(author) {
+                    author_index.entry(token).or_default().push(book.id);
+                }
+            }
+        }
+
+        *self.title_index.write().unwrap() = title_index;
+        *self.author_index.write().unwrap() = author_index;
+    }
+
+    /// Matches each query term as a prefix against the indexed tokens,
+    /// then ranks book ids by the weighted count of distinct query terms
+    /// matched, with title hits counting for more than author hits.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<i32> {
+        let terms: HashSet<String> = tokenize(query).into_iter().collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let title_index = self.title_index.read().unwrap();
+        let author_index = self.author_index.read().unwrap();
+
+        let mut scores: HashMap<i32, i32> = HashMap::new();
+        for term in &terms {
+            for ids in Self::matching_postings(&title_index, term) {
+                *scores.entry(ids).or_insert(0) += TITLE_WEIGHT;
+            }
+            for ids in Self::matching_postings(&author_index, term) {
+                *scores.entry(ids).or_insert(0) += AUTHOR_WEIGHT;
+            }
+        }
+
+        let mut ranked: Vec<(i32, i32)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked.truncate(limit);
+        ranked.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Returns the distinct book ids whose postings match `term` as a
+    /// prefix, so a query like "rus" hits the "rust" token.
+    fn matching_postings(index: &HashMap<String, Vec<i32>>, term: &str) -> HashSet<i32> {
+        let mut hits = HashSet::new();
+        for (token, ids) in index.iter() {
+            if token.starts_with(term) {
+                hits.extend(ids.iter().copied());
+            }
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn book(id: i32, title: &str, authors: &[&str]) -> Book {
+        let mut book = Book::new(
+            id,
+            title.to_string(),
+            authors.iter().map(|a| a.to_string()).collect(),
+            NaiveDate::from_ymd(2020, 1, 1),
+        );
+        book.id = id;
+        book
+    }
+
+    #[test]
+    fn tokenize_lowercases_splits_on_punctuation_and_drops_stop_words() {
+        assert_eq!(
+            tokenize("The Hobbit: An Unexpected Journey"),
+            vec!["hobbit", "unexpected", "journey"]
+        );
+    }
+
+    #[test]
+    fn search_ranks_title_hits_above_author_hits() {
+        let index = SearchIndex::new();
+        index.rebuild(&[
+            book(1, "Rust in Action", &["Tim McNamara"]),
+            book(2, "Programming", &["Rust Cole"]),
+        ]);
+
+        let results = index.search("rust", 10);
+
+        assert_eq!(results, vec![1, 2]);
+    }
+
+    #[test]
+    fn search_matches_by_prefix() {
+        let index = SearchIndex::new();
+        index.rebuild(&[book(1, "Rust in Action", &["Tim McNamara"])]);
+
+        assert_eq!(index.search("rus", 10), vec![1]);
+    }
+
+    #[test]
+    fn search_returns_nothing_for_an_empty_query() {
+        let index = SearchIndex::new();
+        index.rebuild(&[book(1, "Rust in Action", &["Tim McNamara"])]);
+
+        assert!(index.search("", 10).is_empty());
+    }
+}
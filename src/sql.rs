@@ -0,0 +1,66 @@
+//! Small helpers for safely splicing caller-controlled identifiers and
+//! literals into dynamically built SQL (e.g. an `ORDER BY` column chosen
+//! by a query parameter). Values that can instead be passed as bound
+//! parameters should be - these exist for the handful of cases, like
+//! column/table names, where the driver has no parameter slot for them.
+
+/// Wraps a column/table name in double quotes, doubling any embedded
+/// `"` so it can't break out of the identifier.
+pub fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Wraps a value in single quotes, doubling any embedded `'`. If the
+/// value contains a backslash, switches to Postgres's `E'...'`
+/// escape-string form and escapes the backslashes too, so a literal
+/// backslash can't be (mis)interpreted as the start of an escape.
+///
+/// Kept as a general-purpose escaping helper alongside `quote_identifier`
+/// even though today's callers pass values as bound parameters instead -
+/// it's the safe way to splice a literal for any future caller that
+/// can't use a bind parameter (e.g. building a value list outside of a
+/// prepared statement).
+pub fn quote_literal(value: &str) -> String {
+    let escaped = value.replace('\'', "''");
+
+    if value.contains('\\') {
+        format!("E'{}'", escaped.replace('\\', "\\\\"))
+    } else {
+        format!("'{}'", escaped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_identifier_wraps_a_plain_name() {
+        assert_eq!(quote_identifier("publication_date"), "\"publication_date\"");
+    }
+
+    #[test]
+    fn quote_identifier_doubles_embedded_quotes() {
+        assert_eq!(quote_identifier("weird\"col"), "\"weird\"\"col\"");
+    }
+
+    #[test]
+    fn quote_literal_wraps_a_plain_value() {
+        assert_eq!(quote_literal("Rust"), "'Rust'");
+    }
+
+    #[test]
+    fn quote_literal_doubles_embedded_single_quotes() {
+        assert_eq!(quote_literal("O'Brien"), "'O''Brien'");
+    }
+
+    #[test]
+    fn quote_literal_escapes_backslashes_as_an_e_string() {
+        assert_eq!(quote_literal("a\\b"), "E'a\\\\b'");
+    }
+
+    #[test]
+    fn quote_literal_escapes_both_quotes_and_backslashes_together() {
+        assert_eq!(quote_literal("it's a\\test"), "E'it''s a\\\\test'");
+    }
+}